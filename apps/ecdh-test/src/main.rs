@@ -7,6 +7,7 @@ use num_traits::ToPrimitive;
 use gam::UxRegistration;
 use xous_ipc::Buffer;
 
+mod peer;
 mod ui;
 use ui::EcdhTestUi;
 
@@ -20,6 +21,17 @@ pub enum EcdhTestOp {
     ChangeFocus = 2,
     /// Quit the app
     Quit = 3,
+    /// Raw key event, used to scroll the transcript
+    RawKeys = 4,
+    /// A `KeyExchangeClient::exchange` request: lend_mut a 32-byte public key, we
+    /// overwrite the buffer with ours before returning it.
+    PeerExchange = 5,
+    /// A `KeyExchangeClient::send_public` request: lend a 32-byte public key for us
+    /// to hold until the sender's peer polls for it.
+    PeerSendPublic = 6,
+    /// A `KeyExchangeClient::poll_public` request: lend_mut a placeholder, we overwrite
+    /// it with the pending public key (if any) from the last `PeerSendPublic`.
+    PeerPollPublic = 7,
 }
 
 const SERVER_NAME_ECDH_TEST: &str = "_ECDH Test App_";
@@ -43,7 +55,7 @@ fn main() -> ! {
             redraw_id: EcdhTestOp::Redraw.to_u32().unwrap(),
             gotinput_id: Some(EcdhTestOp::Line.to_u32().unwrap()),
             audioframe_id: None,
-            rawkeys_id: None,
+            rawkeys_id: Some(EcdhTestOp::RawKeys.to_u32().unwrap()),
             focuschange_id: Some(EcdhTestOp::ChangeFocus.to_u32().unwrap()),
         })
         .expect("couldn't register Ux context");
@@ -80,6 +92,34 @@ fn main() -> ! {
             Some(EcdhTestOp::ChangeFocus) => {
                 // Focus change - we don't need to do anything special
             }
+            Some(EcdhTestOp::RawKeys) => {
+                if let Some(scalar) = msg.body.scalar_message() {
+                    for &key in &[scalar.arg1, scalar.arg2, scalar.arg3, scalar.arg4] {
+                        if let Some(c) = char::from_u32(key as u32) {
+                            ui.handle_rawkey(c);
+                        }
+                    }
+                    ui.redraw().ok();
+                }
+            }
+            Some(EcdhTestOp::PeerExchange) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let peer_bytes: [u8; 32] = buffer.to_original().unwrap();
+                let our_bytes = ui.respond_exchange(peer_bytes);
+                buffer.replace(our_bytes).ok();
+            }
+            Some(EcdhTestOp::PeerSendPublic) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let peer_bytes: [u8; 32] = buffer.to_original().unwrap();
+                ui.store_pending_peer(peer_bytes);
+            }
+            Some(EcdhTestOp::PeerPollPublic) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let pending = ui.take_pending_peer();
+                buffer.replace(pending).ok();
+            }
             Some(EcdhTestOp::Quit) => {
                 info!("Quit requested, exiting");
                 break;
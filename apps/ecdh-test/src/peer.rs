@@ -0,0 +1,93 @@
+use num_traits::ToPrimitive;
+use x25519_dalek::PublicKey;
+use xous_ipc::Buffer;
+
+use crate::EcdhTestOp;
+
+/// A counterparty for an ECDH handshake. `LoopbackPeer` fakes one in-process; `NetworkPeer`
+/// talks to a real peer process, so `cmd_run` can be pointed at either without caring which.
+pub trait KeyExchangeClient {
+    /// Synchronously swaps public keys with the peer and returns theirs.
+    ///
+    /// Blocks this process's single message-handling thread until the peer responds.
+    /// For a `NetworkPeer`, that means: never call this from both ends of the same
+    /// connection at once — each side would be blocked waiting inside its own
+    /// `exchange` call and unable to service the other's `PeerExchange` request, so
+    /// neither ever responds. Use `send_public`/`poll_public` instead for a live,
+    /// genuinely bidirectional connection.
+    fn exchange(&mut self, our_public: &PublicKey) -> Result<PublicKey, xous::Error>;
+
+    /// Hands our public key to the peer without waiting for theirs back.
+    fn send_public(&mut self, our_public: &PublicKey) -> Result<(), xous::Error>;
+
+    /// Non-blocking check for a public key the peer has sent us via `send_public`.
+    fn poll_public(&mut self) -> Result<Option<PublicKey>, xous::Error>;
+}
+
+/// Generates a fresh peer keypair in-process, matching the app's original fake-counterparty
+/// behavior. Used as the default peer until `connect` is given a real server name.
+pub struct LoopbackPeer {
+    trng: trng::Trng,
+}
+
+impl LoopbackPeer {
+    pub fn new(xns: &xous_names::XousNames) -> Self {
+        Self { trng: trng::Trng::new(xns).expect("couldn't get TRNG") }
+    }
+
+    fn random_public(&mut self) -> PublicKey {
+        let mut secret_bytes = [0u8; 32];
+        self.trng.fill_bytes_via_next(&mut secret_bytes);
+        let secret = x25519_dalek::StaticSecret::from(secret_bytes);
+        PublicKey::from(&secret)
+    }
+}
+
+impl KeyExchangeClient for LoopbackPeer {
+    fn exchange(&mut self, _our_public: &PublicKey) -> Result<PublicKey, xous::Error> {
+        Ok(self.random_public())
+    }
+
+    fn send_public(&mut self, _our_public: &PublicKey) -> Result<(), xous::Error> { Ok(()) }
+
+    fn poll_public(&mut self) -> Result<Option<PublicKey>, xous::Error> { Ok(Some(self.random_public())) }
+}
+
+/// Talks to another instance of this app over Xous IPC, so two real processes can run
+/// the handshake against each other instead of against a locally faked key.
+pub struct NetworkPeer {
+    conn: xous::CID,
+}
+
+impl NetworkPeer {
+    pub fn new(xns: &xous_names::XousNames, server_name: &str) -> Result<Self, xous::Error> {
+        let conn = xns.request_connection_blocking(server_name).map_err(|_| xous::Error::InternalError)?;
+        Ok(Self { conn })
+    }
+}
+
+impl KeyExchangeClient for NetworkPeer {
+    // See the trait-level doc comment: this blocks on `lend_mut` and will deadlock
+    // against a peer that is simultaneously blocked inside its own `exchange` call.
+    fn exchange(&mut self, our_public: &PublicKey) -> Result<PublicKey, xous::Error> {
+        let mut buf = Buffer::into_buf(*our_public.as_bytes()).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, EcdhTestOp::PeerExchange.to_u32().unwrap())
+            .or(Err(xous::Error::InternalError))?;
+        let peer_bytes: [u8; 32] = buf.to_original().or(Err(xous::Error::InternalError))?;
+        Ok(PublicKey::from(peer_bytes))
+    }
+
+    fn send_public(&mut self, our_public: &PublicKey) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(*our_public.as_bytes()).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, EcdhTestOp::PeerSendPublic.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        Ok(())
+    }
+
+    fn poll_public(&mut self) -> Result<Option<PublicKey>, xous::Error> {
+        let mut buf = Buffer::into_buf([0u8; 32]).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, EcdhTestOp::PeerPollPublic.to_u32().unwrap())
+            .or(Err(xous::Error::InternalError))?;
+        let result: Option<[u8; 32]> = buf.to_original().or(Err(xous::Error::InternalError))?;
+        Ok(result.map(PublicKey::from))
+    }
+}
@@ -4,17 +4,118 @@ use log::info;
 use gam::{Gam, Point, Rectangle, DrawStyle, PixelColor, TextView, TextBounds, GlyphStyle, Gid};
 use xous::String as XousString;
 
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
 use x25519_dalek::{PublicKey, StaticSecret};
 
-/// Maximum number of messages to keep in history
-const MAX_HISTORY: usize = 20;
+use crate::peer::{KeyExchangeClient, LoopbackPeer, NetworkPeer};
+
+/// Maximum number of transcript entries to keep around for scrollback
+const MAX_HISTORY: usize = 64;
+
+/// Maximum size of the raw byte blob a transcript entry can hold (enough for any key,
+/// shared secret, or nonce+tag the app deals with).
+const MAX_ENTRY_RAW: usize = 64;
+
+/// Fixed HKDF salt for the demo session key derivation. A real protocol would negotiate
+/// or transmit this; here it just needs to be consistent between extract and expand.
+const SESSION_HKDF_SALT: &[u8] = b"xous-ecdh-test hkdf salt";
+const SESSION_HKDF_INFO: &[u8] = b"xous-ecdh-test chacha20poly1305";
+
+/// Largest plaintext `cmd_encrypt` will accept. Sized so the resulting
+/// nonce+ciphertext+tag payload always fits its fixed-size payload buffer; the hex
+/// is then printed in 16-byte rows, each short enough for one transcript entry.
+const MAX_ENCRYPT_PLAINTEXT_LEN: usize = 64;
+const MAX_ENCRYPT_PAYLOAD_LEN: usize = 12 + MAX_ENCRYPT_PLAINTEXT_LEN + 16;
+
+/// Control codes the keyboard driver sends for the up/down arrow keys via `rawkeys_id`.
+const KEY_UP: char = '\u{11}';
+const KEY_DOWN: char = '\u{12}';
+
+/// Signature shared by every entry in `COMMANDS`, so new commands can be added without
+/// growing a match arm in `handle_input`.
+type CommandHandler = fn(&mut EcdhTestUi, &str);
+
+struct Command {
+    name: &'static str,
+    help: &'static str,
+    handler: CommandHandler,
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "run",
+        help: "Run the ECDH handshake demo (don't run on both ends of a live 'connect')",
+        handler: EcdhTestUi::cmd_run,
+    },
+    Command {
+        name: "encrypt",
+        help: "Encrypt <text> with the session key from the last 'run'",
+        handler: EcdhTestUi::cmd_encrypt,
+    },
+    Command {
+        name: "decrypt",
+        help: "Decrypt a hex ciphertext from 'encrypt'",
+        handler: EcdhTestUi::cmd_decrypt,
+    },
+    Command { name: "clear", help: "Clear the history", handler: EcdhTestUi::cmd_clear },
+    Command {
+        name: "show",
+        help: "Toggle the hex dump for transcript entry <seq>",
+        handler: EcdhTestUi::cmd_show,
+    },
+    Command { name: "help", help: "List available commands", handler: EcdhTestUi::cmd_help },
+    Command {
+        name: "connect",
+        help: "Pick the ECDH peer: 'connect loopback' or 'connect <server-name>'",
+        handler: EcdhTestUi::cmd_connect,
+    },
+    Command {
+        name: "announce",
+        help: "Send our public key to the peer without waiting for theirs back",
+        handler: EcdhTestUi::cmd_announce,
+    },
+    Command {
+        name: "collect",
+        help: "Check whether the peer has sent back a public key since 'announce'",
+        handler: EcdhTestUi::cmd_collect,
+    },
+];
+
+/// One step of a handshake transcript: a label, an optional raw byte blob backing it
+/// (a key, secret, or ciphertext), and a sequence number used to address it with `show`.
+struct TranscriptEntry {
+    seq: u32,
+    label: XousString<64>,
+    raw: Option<heapless::Vec<u8, MAX_ENTRY_RAW>>,
+    expanded: bool,
+}
 
 pub struct EcdhTestUi {
     gam: Gam,
     token: [u32; 4],
     content_canvas: Gid,
     screensize: Point,
-    history: heapless::Vec<XousString<512>, MAX_HISTORY>,
+    history: heapless::Vec<TranscriptEntry, MAX_HISTORY>,
+    next_seq: u32,
+    /// Number of most-recent entries scrolled past, so up/down can page through
+    /// more transcript than fits on screen at once.
+    scroll_offset: usize,
+    /// AEAD key derived from the most recent ECDH shared secret, reused across
+    /// successive `encrypt`/`decrypt` commands to form a session.
+    session_key: Option<[u8; 32]>,
+    /// Counterparty for `cmd_run`'s handshake; swapped out by `connect`.
+    peer: Box<dyn KeyExchangeClient>,
+    /// Public key handed to us by a `NetworkPeer` via `PeerSendPublic`, waiting to be
+    /// picked up by that peer's `poll_public`.
+    pending_peer_public: Option<PublicKey>,
+    /// Our private key from the last `announce`, held until `collect` gets the peer's
+    /// public key back and can finish the ECDH.
+    pending_our_secret: Option<[u8; 32]>,
 }
 
 impl EcdhTestUi {
@@ -28,18 +129,45 @@ impl EcdhTestUi {
             content_canvas,
             screensize,
             history: heapless::Vec::new(),
+            next_seq: 0,
+            scroll_offset: 0,
+            session_key: None,
+            peer: Box::new(LoopbackPeer::new(xns)),
+            pending_peer_public: None,
+            pending_our_secret: None,
         }
     }
 
+    /// Appends a plain log line with no raw byte payload, e.g. input echo or status text.
     pub fn add_message(&mut self, msg: &str) {
-        let mut xstr = XousString::<512>::new();
-        write!(xstr, "{}", msg).ok();
+        self.add_entry(msg, None);
+    }
+
+    /// Appends a transcript entry. When `raw` is `Some`, the entry renders as a compact
+    /// hex preview by default and can be expanded into a full hex dump with `show <seq>`.
+    pub fn add_entry(&mut self, label: &str, raw: Option<&[u8]>) {
+        let mut entry_label = XousString::<64>::new();
+        write!(entry_label, "{}", label).ok();
+
+        let entry = TranscriptEntry {
+            seq: self.next_seq,
+            label: entry_label,
+            raw: raw.map(|bytes| {
+                let mut v: heapless::Vec<u8, MAX_ENTRY_RAW> = heapless::Vec::new();
+                v.extend_from_slice(bytes).ok();
+                v
+            }),
+            expanded: false,
+        };
+        self.next_seq = self.next_seq.wrapping_add(1);
 
         // Circular buffer behavior
         if self.history.len() >= MAX_HISTORY {
             self.history.remove(0);
         }
-        self.history.push(xstr).ok();
+        self.history.push(entry).ok();
+        // Jump back to the bottom so new activity is always visible.
+        self.scroll_offset = 0;
     }
 
     fn format_hex(bytes: &[u8]) -> XousString<256> {
@@ -50,6 +178,38 @@ impl EcdhTestUi {
         result
     }
 
+    /// Compact single-line preview of `bytes`, used for an entry's collapsed summary.
+    fn format_hex_preview(bytes: &[u8]) -> XousString<64> {
+        let mut s = XousString::new();
+        let preview_len = bytes.len().min(8);
+        for b in &bytes[..preview_len] {
+            write!(s, "{:02x} ", b).ok();
+        }
+        if bytes.len() > preview_len {
+            write!(s, "...").ok();
+        }
+        s
+    }
+
+    /// Parses a (whitespace-tolerant) hex string back into bytes, ignoring any
+    /// non-hex-digit characters such as the spaces `format_hex` inserts.
+    fn parse_hex(s: &str) -> heapless::Vec<u8, 256> {
+        let mut bytes: heapless::Vec<u8, 256> = heapless::Vec::new();
+        let mut hi: Option<u8> = None;
+        for c in s.chars() {
+            if let Some(nibble) = c.to_digit(16) {
+                match hi {
+                    None => hi = Some(nibble as u8),
+                    Some(h) => {
+                        bytes.push((h << 4) | nibble as u8).ok();
+                        hi = None;
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
     fn bytes_to_log_string(bytes: &[u8]) -> XousString<256> {
         let mut s = XousString::new();
         for (i, b) in bytes.iter().enumerate() {
@@ -61,6 +221,73 @@ impl EcdhTestUi {
         s
     }
 
+    /// Renders one transcript entry into the lines `redraw` draws bottom-up: a single
+    /// compact line normally, or a header plus a 16-bytes-per-row hex dump when expanded.
+    fn render_lines(entry: &TranscriptEntry) -> heapless::Vec<XousString<160>, 8> {
+        let mut lines: heapless::Vec<XousString<160>, 8> = heapless::Vec::new();
+        match &entry.raw {
+            Some(bytes) if entry.expanded => {
+                let mut header = XousString::new();
+                write!(
+                    header,
+                    "#{} {} ({} bytes):",
+                    entry.seq,
+                    entry.label.as_str().unwrap_or(""),
+                    bytes.len()
+                )
+                .ok();
+                lines.push(header).ok();
+
+                let dump = Self::bytes_to_log_string(bytes);
+                for row in dump.as_str().unwrap_or("").split('\n') {
+                    let mut line = XousString::new();
+                    write!(line, "{}", row).ok();
+                    lines.push(line).ok();
+                }
+            }
+            Some(bytes) => {
+                let mut line = XousString::new();
+                write!(
+                    line,
+                    "#{} {}: {}",
+                    entry.seq,
+                    entry.label.as_str().unwrap_or(""),
+                    Self::format_hex_preview(bytes).as_str().unwrap_or("")
+                )
+                .ok();
+                lines.push(line).ok();
+            }
+            None => {
+                let mut line = XousString::new();
+                write!(line, "{}", entry.label.as_str().unwrap_or("")).ok();
+                lines.push(line).ok();
+            }
+        }
+        lines
+    }
+
+    /// Scrolls the transcript view one entry further into the past.
+    fn scroll_up(&mut self) {
+        if self.scroll_offset + 1 < self.history.len() {
+            self.scroll_offset += 1;
+        }
+    }
+
+    /// Scrolls the transcript view one entry back towards the present.
+    fn scroll_down(&mut self) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(1);
+    }
+
+    /// Handles a raw key event forwarded by the GAM (see `rawkeys_id`); only the arrow
+    /// keys used for scrollback are recognized, everything else is ignored.
+    pub fn handle_rawkey(&mut self, key: char) {
+        match key {
+            KEY_UP => self.scroll_up(),
+            KEY_DOWN => self.scroll_down(),
+            _ => {}
+        }
+    }
+
     pub fn handle_input(&mut self, input: &str) {
         // Echo input
         let mut echo = XousString::<512>::new();
@@ -68,22 +295,282 @@ impl EcdhTestUi {
         self.add_message(echo.as_str().unwrap_or(""));
 
         let trimmed = input.trim();
+        if trimmed.is_empty() {
+            return;
+        }
+        let (name, arg) = match trimmed.split_once(' ') {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (trimmed, ""),
+        };
+
+        if let Some(command) = Self::find_command(name) {
+            (command.handler)(self, arg);
+            return;
+        }
 
-        match trimmed {
-            "run" => {
-                self.cmd_run();
+        match Self::best_match(name) {
+            Some(suggestion) => {
+                let mut msg = XousString::<512>::new();
+                write!(msg, "Unknown command '{}', did you mean '{}'?", name, suggestion).ok();
+                self.add_message(msg.as_str().unwrap_or(""));
             }
-            "clear" => {
-                self.history.clear();
-                self.add_message("Screen cleared");
+            None => {
+                self.add_message("Unknown command, type 'help' for a list");
             }
-            _ => {
-                self.add_message("Type 'run' to test ECDH");
+        }
+    }
+
+    /// Looks up `name` by exact match, falling back to an unambiguous prefix match
+    /// (e.g. `enc` resolves to `encrypt` as long as no other command also starts with it).
+    /// Deliberately does NOT auto-dispatch on a fuzzy subsequence match (see `best_match`):
+    /// with destructive commands like `clear` in the table, silently running the shortest
+    /// name that merely contains the typed letters in order is too easy to trigger by
+    /// accident (e.g. a bare `c` would pick `clear` over `connect`/`collect`).
+    fn find_command(name: &str) -> Option<&'static Command> {
+        if let Some(command) = COMMANDS.iter().find(|c| c.name == name) {
+            return Some(command);
+        }
+        let mut matches = COMMANDS.iter().filter(|c| c.name.starts_with(name));
+        let first = matches.next()?;
+        if matches.next().is_none() { Some(first) } else { None }
+    }
+
+    /// True if every character of `needle` appears in `haystack`, in order, allowing gaps
+    /// in between (so `rn` matches `run`, `dcrpt` matches `decrypt`).
+    fn is_subsequence(needle: &str, haystack: &str) -> bool {
+        let mut haystack = haystack.chars();
+        needle.chars().all(|c| haystack.any(|h| h.eq_ignore_ascii_case(&c)))
+    }
+
+    /// Picks the shortest registered command name that `input` is a subsequence of, to
+    /// suggest a correction for unrecognized input.
+    fn best_match(input: &str) -> Option<&'static str> {
+        COMMANDS
+            .iter()
+            .filter(|c| Self::is_subsequence(input, c.name))
+            .min_by_key(|c| c.name.len())
+            .map(|c| c.name)
+    }
+
+    fn cmd_clear(&mut self, _arg: &str) {
+        self.history.clear();
+        self.scroll_offset = 0;
+        self.add_message("Screen cleared");
+    }
+
+    /// Toggles the expanded hex dump for the transcript entry with sequence number `arg`.
+    fn cmd_show(&mut self, arg: &str) {
+        match arg.trim().parse::<u32>() {
+            Ok(seq) => match self.history.iter_mut().find(|e| e.seq == seq) {
+                Some(entry) => entry.expanded = !entry.expanded,
+                None => self.add_message("No such transcript entry"),
+            },
+            Err(_) => self.add_message("Usage: show <seq>"),
+        }
+    }
+
+    /// Switches which `KeyExchangeClient` `cmd_run` performs its handshake against.
+    fn cmd_connect(&mut self, arg: &str) {
+        let xns = xous_names::XousNames::new().unwrap();
+        if arg.is_empty() || arg == "loopback" {
+            self.peer = Box::new(LoopbackPeer::new(&xns));
+            self.forget_pending_exchange();
+            self.add_message("Connected to loopback peer");
+            return;
+        }
+
+        match NetworkPeer::new(&xns, arg) {
+            Ok(network_peer) => {
+                self.peer = Box::new(network_peer);
+                self.forget_pending_exchange();
+                let mut msg = XousString::<512>::new();
+                write!(msg, "Connected to peer '{}'", arg).ok();
+                self.add_message(msg.as_str().unwrap_or(""));
             }
+            Err(_) => {
+                self.add_message("Couldn't connect to peer server");
+            }
+        }
+    }
+
+    /// Drops any in-flight `announce`/`collect` state. Called whenever the peer changes,
+    /// so a `collect` never pairs the secret from one handshake with the public key of
+    /// another, unrelated one.
+    fn forget_pending_exchange(&mut self) {
+        self.pending_our_secret = None;
+        self.pending_peer_public = None;
+    }
+
+    /// Generates a keypair and hands the public half to the peer via `send_public`,
+    /// without blocking for a reply (see `cmd_collect`).
+    fn cmd_announce(&mut self, _arg: &str) {
+        let xns = xous_names::XousNames::new().unwrap();
+        let mut trng = trng::Trng::new(&xns).expect("couldn't get TRNG");
+        let mut our_secret_bytes = [0u8; 32];
+        trng.fill_bytes_via_next(&mut our_secret_bytes);
+        let our_public = PublicKey::from(&StaticSecret::from(our_secret_bytes));
+
+        match self.peer.send_public(&our_public) {
+            Ok(()) => {
+                self.pending_our_secret = Some(our_secret_bytes);
+                self.add_entry("Announced our public key", Some(our_public.as_bytes()));
+            }
+            Err(_) => {
+                self.add_message("Announce failed");
+            }
+        }
+    }
+
+    /// Polls the peer for the public key it may have sent back since `cmd_announce`,
+    /// and finishes the ECDH (deriving a session key) once one arrives.
+    fn cmd_collect(&mut self, _arg: &str) {
+        let Some(our_secret_bytes) = self.pending_our_secret else {
+            self.add_message("Nothing announced yet, run 'announce' first");
+            return;
+        };
+
+        match self.peer.poll_public() {
+            Ok(Some(peer_public)) => {
+                self.pending_our_secret = None;
+                self.add_entry("Collected peer public key", Some(peer_public.as_bytes()));
+
+                let our_secret = StaticSecret::from(our_secret_bytes);
+                let shared_secret = our_secret.diffie_hellman(&peer_public);
+                self.add_entry("ECDH output: shared secret", Some(shared_secret.as_bytes()));
+
+                let hk = Hkdf::<Sha256>::new(Some(SESSION_HKDF_SALT), shared_secret.as_bytes());
+                let mut session_key = [0u8; 32];
+                hk.expand(SESSION_HKDF_INFO, &mut session_key).expect("HKDF expand failed");
+                self.add_entry("Session key", Some(&session_key));
+                self.session_key = Some(session_key);
+            }
+            Ok(None) => {
+                self.add_message("No peer key yet, try 'collect' again later");
+            }
+            Err(_) => {
+                self.add_message("Collect failed");
+            }
+        }
+    }
+
+    /// Responds to a remote `KeyExchangeClient::exchange` call: records the caller's
+    /// public key as an entry and returns a fresh public key of our own.
+    pub fn respond_exchange(&mut self, peer_bytes: [u8; 32]) -> [u8; 32] {
+        let xns = xous_names::XousNames::new().unwrap();
+        let mut trng = trng::Trng::new(&xns).expect("couldn't get TRNG");
+        let mut secret_bytes = [0u8; 32];
+        trng.fill_bytes_via_next(&mut secret_bytes);
+        let our_public = PublicKey::from(&StaticSecret::from(secret_bytes));
+
+        self.add_entry("Peer exchange: received public key", Some(&peer_bytes));
+        *our_public.as_bytes()
+    }
+
+    /// Responds to a remote `KeyExchangeClient::send_public` call by stashing the
+    /// caller's public key until our own peer polls for it.
+    pub fn store_pending_peer(&mut self, peer_bytes: [u8; 32]) {
+        self.pending_peer_public = Some(PublicKey::from(peer_bytes));
+        self.add_entry("Peer exchange: stored public key", Some(&peer_bytes));
+    }
+
+    /// Responds to a remote `KeyExchangeClient::poll_public` call, handing over and
+    /// clearing any public key stashed by `store_pending_peer`.
+    pub fn take_pending_peer(&mut self) -> Option<[u8; 32]> {
+        self.pending_peer_public.take().map(|pk| *pk.as_bytes())
+    }
+
+    fn cmd_help(&mut self, _arg: &str) {
+        self.add_message("Available commands:");
+        for command in COMMANDS {
+            let mut msg = XousString::<512>::new();
+            write!(msg, "  {} - {}", command.name, command.help).ok();
+            self.add_message(msg.as_str().unwrap_or(""));
         }
     }
 
-    fn cmd_run(&mut self) {
+    /// Encrypts `plaintext` under the session key with a fresh random nonce, and
+    /// reports the nonce-prefixed ciphertext+tag as hex.
+    fn cmd_encrypt(&mut self, plaintext: &str) {
+        let Some(session_key) = self.session_key else {
+            self.add_message("No session key yet, run 'run' first");
+            return;
+        };
+
+        if plaintext.len() > MAX_ENCRYPT_PLAINTEXT_LEN {
+            let mut msg = XousString::<512>::new();
+            write!(msg, "Plaintext too long (max {} bytes)", MAX_ENCRYPT_PLAINTEXT_LEN).ok();
+            self.add_message(msg.as_str().unwrap_or(""));
+            return;
+        }
+
+        let xns = xous_names::XousNames::new().unwrap();
+        let mut trng = trng::Trng::new(&xns).expect("couldn't get TRNG");
+        let mut nonce_bytes = [0u8; 12];
+        trng.fill_bytes_via_next(&mut nonce_bytes);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&session_key));
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        match cipher.encrypt(nonce, plaintext.as_bytes()) {
+            Ok(ciphertext) => {
+                let mut payload: heapless::Vec<u8, MAX_ENCRYPT_PAYLOAD_LEN> = heapless::Vec::new();
+                if payload.extend_from_slice(&nonce_bytes).is_err()
+                    || payload.extend_from_slice(&ciphertext).is_err()
+                {
+                    self.add_message("Ciphertext too large to display");
+                    return;
+                }
+
+                // One transcript line per 16 payload bytes, so each "Enc: " row fits an
+                // entry's 64-character label; `decrypt` takes the rows' hex concatenated.
+                for chunk in payload.chunks(16) {
+                    let mut msg = XousString::<512>::new();
+                    write!(msg, "Enc: {}", Self::format_hex(chunk).as_str().unwrap()).ok();
+                    self.add_message(msg.as_str().unwrap_or(""));
+                }
+            }
+            Err(_) => {
+                self.add_message("Encryption failed");
+            }
+        }
+    }
+
+    /// Decrypts a nonce-prefixed hex ciphertext produced by `cmd_encrypt` under the
+    /// session key, reporting an authentication failure instead of garbage plaintext.
+    fn cmd_decrypt(&mut self, hex: &str) {
+        let Some(session_key) = self.session_key else {
+            self.add_message("No session key yet, run 'run' first");
+            return;
+        };
+
+        let payload = Self::parse_hex(hex);
+        if payload.len() < 12 {
+            self.add_message("Ciphertext too short");
+            return;
+        }
+        let (nonce_bytes, ciphertext) = payload.split_at(12);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&session_key));
+        let nonce = Nonce::from_slice(nonce_bytes);
+        match cipher.decrypt(nonce, ciphertext) {
+            Ok(plaintext) => {
+                let mut msg = XousString::<512>::new();
+                match core::str::from_utf8(&plaintext) {
+                    Ok(text) => write!(msg, "Dec: {}", text).ok(),
+                    Err(_) => write!(msg, "Dec: <invalid utf8>").ok(),
+                };
+                self.add_message(msg.as_str().unwrap_or(""));
+            }
+            Err(_) => {
+                self.add_message("Decryption failed: authentication check failed");
+            }
+        }
+    }
+
+    /// Runs the ECDH handshake against the connected peer. Calls `KeyExchangeClient::exchange`,
+    /// which for a `NetworkPeer` blocks this process until the peer responds — safe against a
+    /// loopback peer or an idle remote one, but deadlocks if the remote side is also blocked in
+    /// its own `run` waiting on us. Use `announce`/`collect` for a live, bidirectional peer.
+    fn cmd_run(&mut self, _arg: &str) {
         info!("=== STARTING ECDH TEST ===");
         self.add_message("=== ECDH TEST ===");
 
@@ -100,42 +587,35 @@ impl EcdhTestUi {
 
         info!("Our private key: {}", Self::bytes_to_log_string(&our_secret_bytes).as_str().unwrap());
         info!("Our public key: {}", Self::bytes_to_log_string(our_public.as_bytes()).as_str().unwrap());
+        self.add_entry("Our private key", Some(&our_secret_bytes));
+        self.add_entry("Our public key", Some(our_public.as_bytes()));
+
+        // Get the peer's public key from whichever `KeyExchangeClient` is connected
+        // (a real process for `NetworkPeer`, an in-process fake for `LoopbackPeer`).
+        self.add_message("2. Exchanging keys with peer...");
+        let peer_public = match self.peer.exchange(&our_public) {
+            Ok(peer_public) => peer_public,
+            Err(_) => {
+                self.add_message("Peer exchange failed");
+                return;
+            }
+        };
 
-        let mut msg = XousString::<512>::new();
-        write!(msg, "Our priv: {}", Self::format_hex(&our_secret_bytes).as_str().unwrap()).ok();
-        self.add_message(msg.as_str().unwrap_or(""));
-
-        let mut msg = XousString::<512>::new();
-        write!(msg, "Our pub:  {}", Self::format_hex(our_public.as_bytes()).as_str().unwrap()).ok();
-        self.add_message(msg.as_str().unwrap_or(""));
-
-        // Generate peer's keypair
-        self.add_message("2. Generating peer keypair...");
-        let mut peer_secret_bytes = [0u8; 32];
-        trng.fill_bytes_via_next(&mut peer_secret_bytes);
-        let peer_secret = StaticSecret::from(peer_secret_bytes);
-        let peer_public = PublicKey::from(&peer_secret);
-
-        info!("Peer private key: {}", Self::bytes_to_log_string(&peer_secret_bytes).as_str().unwrap());
         info!("Peer public key: {}", Self::bytes_to_log_string(peer_public.as_bytes()).as_str().unwrap());
-
-        let mut msg = XousString::<512>::new();
-        write!(msg, "Peer pub: {}", Self::format_hex(peer_public.as_bytes()).as_str().unwrap()).ok();
-        self.add_message(msg.as_str().unwrap_or(""));
+        self.add_entry("Peer public key", Some(peer_public.as_bytes()));
 
         // Perform ECDH: our_private * peer_public
         self.add_message("3. Computing ECDH...");
         info!("Computing ECDH: our_private.diffie_hellman(peer_public)");
         info!("  Input private: {}", Self::bytes_to_log_string(&our_secret_bytes).as_str().unwrap());
         info!("  Input public:  {}", Self::bytes_to_log_string(peer_public.as_bytes()).as_str().unwrap());
+        self.add_entry("ECDH input: our private key", Some(&our_secret_bytes));
+        self.add_entry("ECDH input: peer public key", Some(peer_public.as_bytes()));
 
         let shared_secret = our_secret.diffie_hellman(&peer_public);
 
         info!("  Output shared: {}", Self::bytes_to_log_string(shared_secret.as_bytes()).as_str().unwrap());
-
-        let mut msg = XousString::<512>::new();
-        write!(msg, "Shared:   {}", Self::format_hex(shared_secret.as_bytes()).as_str().unwrap()).ok();
-        self.add_message(msg.as_str().unwrap_or(""));
+        self.add_entry("ECDH output: shared secret", Some(shared_secret.as_bytes()));
 
         // Check for the bug
         self.add_message("4. Checking results...");
@@ -151,6 +631,18 @@ impl EcdhTestUi {
             info!("ECDH output looks correct");
         }
 
+        // Derive a ChaCha20-Poly1305 session key from the shared secret via HKDF-SHA256,
+        // so `encrypt`/`decrypt` can use it without re-running the handshake.
+        self.add_message("5. Deriving session key...");
+        let hk = Hkdf::<Sha256>::new(Some(SESSION_HKDF_SALT), shared_secret.as_bytes());
+        let mut session_key = [0u8; 32];
+        hk.expand(SESSION_HKDF_INFO, &mut session_key).expect("HKDF expand failed");
+
+        info!("Session key: {}", Self::bytes_to_log_string(&session_key).as_str().unwrap());
+        self.add_entry("Session key", Some(&session_key));
+
+        self.session_key = Some(session_key);
+
         info!("=== ECDH TEST COMPLETE ===");
         self.add_message("=== TEST COMPLETE ===");
     }
@@ -172,27 +664,31 @@ impl EcdhTestUi {
             )
             .expect("can't clear canvas");
 
-        // Draw messages from bottom to top
+        // Draw transcript entries from bottom to top, skipping `scroll_offset` of the
+        // most recent ones; within an entry its lines are drawn bottom-up too, so a
+        // multi-line hex dump still reads top-to-bottom on screen.
         let margin = 4;
         let line_height = 16;
         let mut y = self.screensize.y - margin;
 
-        for msg in self.history.iter().rev() {
-            if let Ok(msg_str) = msg.as_str() {
-                let mut tv = TextView::new(
-                    self.content_canvas,
-                    TextBounds::BoundingBox(Rectangle::new(
-                        Point::new(margin, y - line_height),
-                        Point::new(self.screensize.x - margin, y),
-                    )),
-                );
-                tv.style = GlyphStyle::Small;
-                write!(tv.text, "{}", msg_str).ok();
-                self.gam.post_textview(&mut tv).ok();
-
-                y -= line_height;
-                if y < 0 {
-                    break;
+        'entries: for entry in self.history.iter().rev().skip(self.scroll_offset) {
+            for line in Self::render_lines(entry).iter().rev() {
+                if let Ok(line_str) = line.as_str() {
+                    let mut tv = TextView::new(
+                        self.content_canvas,
+                        TextBounds::BoundingBox(Rectangle::new(
+                            Point::new(margin, y - line_height),
+                            Point::new(self.screensize.x - margin, y),
+                        )),
+                    );
+                    tv.style = GlyphStyle::Small;
+                    write!(tv.text, "{}", line_str).ok();
+                    self.gam.post_textview(&mut tv).ok();
+
+                    y -= line_height;
+                    if y < 0 {
+                        break 'entries;
+                    }
                 }
             }
         }